@@ -4,7 +4,25 @@
 
 use std::borrow::Cow;
 
+/// Strips Windows extended-length (`\\?\`) prefixes, yielding the most
+/// compatible form a browser or DevTools will resolve. Ordinary verbatim
+/// drive paths lose the prefix (`\\?\C:\foo` becomes `C:\foo`) and verbatim
+/// UNC paths are rewritten back to the plain UNC form (`\\?\UNC\server\share`
+/// becomes `\\server\share`). Other paths are returned unchanged.
+fn de_verbatim(p: &str) -> Cow<str> {
+  if let Some(rest) = p.strip_prefix(r"\\?\") {
+    if let Some(unc) = rest.strip_prefix(r"UNC\") {
+      Cow::Owned(format!(r"\\{}", unc))
+    } else {
+      Cow::Borrowed(rest)
+    }
+  } else {
+    Cow::Borrowed(p)
+  }
+}
+
 fn is_absolute(p: &str) -> bool {
+  let p = de_verbatim(p);
   let mut bytes = p.bytes();
   let b = match bytes.next() {
     Some(b) => b,
@@ -21,14 +39,157 @@ fn is_absolute(p: &str) -> bool {
   }
 }
 
-fn strip_prefix<'a>(p: &'a str, prefix: &'static str) -> Option<&'a str> {
-  if p.starts_with(prefix) {
-    Some(unsafe { p.get_unchecked(prefix.len()..) })
-  } else {
+/// Returns the byte length of the leading root of `p` together with the
+/// separator character used by that path style. The root is `/` for Unix
+/// paths, `C:\` (or `C:`) for Windows drive paths, and `\\server\share` for
+/// UNC paths.
+fn root_len_and_sep(p: &str) -> (usize, char) {
+  let bytes = p.as_bytes();
+  match bytes.first() {
+    Some(b'/') => (1, '/'),
+    Some(b'\\') if bytes.get(1) == Some(&b'\\') => {
+      // UNC path: `\\server\share`.
+      let mut i = 2;
+      while i < bytes.len() && bytes[i] != b'\\' {
+        i += 1;
+      }
+      if i < bytes.len() {
+        i += 1; // separator between server and share
+      }
+      while i < bytes.len() && bytes[i] != b'\\' {
+        i += 1;
+      }
+      (i, '\\')
+    }
+    Some(b) if b.is_ascii_alphabetic() && bytes.get(1) == Some(&b':') => {
+      // Windows drive path, including a trailing separator if present.
+      let len = match bytes.get(2) {
+        Some(b'\\') | Some(b'/') => 3,
+        _ => 2,
+      };
+      (len, '\\')
+    }
+    _ => (0, '/'),
+  }
+}
+
+/// An ordered list of path-prefix rewrite rules applied by [`Path::to_uri`].
+///
+/// Each rule maps a path prefix to a replacement string. The first rule whose
+/// prefix matches the path wins: the matched prefix is dropped and the rest of
+/// the path is appended to the replacement. When no rule matches, `to_uri`
+/// falls back to plain `file://` URIs. This lets tooling remap paths (for
+/// example `/home/me/project/` to `https://myhost/src/`, or several
+/// rustc-version-pinned mappings) without recompiling.
+pub struct PathMapper {
+  rules: Vec<(String, String)>,
+}
+
+impl PathMapper {
+  /// Creates a mapper with no rules, so every path falls back to `file://`.
+  pub fn new() -> Self {
+    PathMapper { rules: Vec::new() }
+  }
+
+  /// Appends a rule that rewrites paths starting with `prefix` by replacing
+  /// that prefix with `replacement`.
+  pub fn push(&mut self, prefix: impl Into<String>, replacement: impl Into<String>) {
+    self.rules.push((prefix.into(), replacement.into()));
+  }
+
+  /// Returns the remapped form of `path` for the first matching rule, or
+  /// `None` if no rule matches. The portion of the path following the matched
+  /// prefix is percent-encoded so the result is a valid URI.
+  fn remap(&self, path: &str) -> Option<String> {
+    for (prefix, replacement) in &self.rules {
+      if let Some(rest) = path.strip_prefix(prefix.as_str()) {
+        return Some(format!("{}{}", replacement, encode_uri_path(rest)));
+      }
+    }
     None
   }
 }
 
+/// Encodes a filesystem path as the path portion of a file URI per RFC 8089:
+/// backslashes become forward slashes and every byte outside the unreserved
+/// set (`A–Z a–z 0–9 - . _ ~`, plus `/` as the separator and `:` for drive
+/// letters) is percent-encoded as uppercase `%XX`. UTF-8 bytes are encoded
+/// individually.
+fn encode_uri_path(path: &str) -> String {
+  const HEX: &[u8; 16] = b"0123456789ABCDEF";
+  let mut out = String::with_capacity(path.len());
+  for &b in path.as_bytes() {
+    match b {
+      b'\\' => out.push('/'),
+      b'A'..=b'Z'
+      | b'a'..=b'z'
+      | b'0'..=b'9'
+      | b'-'
+      | b'.'
+      | b'_'
+      | b'~'
+      | b'/'
+      | b':' => out.push(b as char),
+      _ => {
+        out.push('%');
+        out.push(HEX[(b >> 4) as usize] as char);
+        out.push(HEX[(b & 0xf) as usize] as char);
+      }
+    }
+  }
+  out
+}
+
+impl Default for PathMapper {
+  /// The default mapper preserves the historical `/rustc/` rewrite to the
+  /// rust-lang GitHub mirror.
+  fn default() -> Self {
+    let mut mapper = PathMapper::new();
+    mapper.push(
+      "/rustc/",
+      "https://raw.githubusercontent.com/rust-lang/rust/",
+    );
+    mapper
+  }
+}
+
+/// Iterator over the components of a [`Path`], yielding the root component
+/// first followed by each normal component. Empty and `.` segments are
+/// skipped. See [`Path::components`].
+pub struct Components<'p> {
+  root: &'p str,
+  rest: &'p str,
+  backslash: bool,
+}
+
+impl<'p> Iterator for Components<'p> {
+  type Item = &'p str;
+
+  fn next(&mut self) -> Option<&'p str> {
+    if !self.root.is_empty() {
+      let root = self.root;
+      self.root = "";
+      return Some(root);
+    }
+    loop {
+      if self.rest.is_empty() {
+        return None;
+      }
+      let (comp, rest) = match self
+        .rest
+        .find(|c| c == '/' || (self.backslash && c == '\\'))
+      {
+        Some(i) => (&self.rest[..i], &self.rest[i + 1..]),
+        None => (self.rest, ""),
+      };
+      self.rest = rest;
+      if !comp.is_empty() && comp != "." {
+        return Some(comp);
+      }
+    }
+  }
+}
+
 pub struct Path<'a>(Cow<'a, str>);
 
 impl<'a> Path<'a> {
@@ -61,19 +222,99 @@ impl<'a> Path<'a> {
     Path(Cow::Borrowed(&self.0))
   }
 
-  pub fn to_uri(&self) -> String {
-    let path = &self.0;
+  /// Lexically normalizes the path in place, collapsing `.` and `..`
+  /// components and redundant separators without touching the filesystem.
+  /// A `..` pops the preceding normal component; a `..` that would escape the
+  /// root is discarded. The leading root and the original separator style are
+  /// preserved.
+  pub fn normalize(&mut self) {
+    let (root_len, sep) = root_len_and_sep(&self.0);
+    let (root, rest) = self.0.split_at(root_len);
+
+    let mut stack: Vec<&str> = Vec::new();
+    for comp in rest.split(|c| c == '/' || (sep == '\\' && c == '\\')) {
+      match comp {
+        "" | "." => {}
+        ".." => match stack.last() {
+          Some(&last) if last != ".." => {
+            stack.pop();
+          }
+          // At the root of an absolute path a stray `..` is dropped;
+          // relative segments keep it.
+          _ if root_len == 0 => stack.push(".."),
+          _ => {}
+        },
+        _ => stack.push(comp),
+      }
+    }
+
+    let joined = stack.join(&sep.to_string());
+    let normalized = if joined.is_empty() {
+      root.to_string()
+    } else if root.is_empty() || root.ends_with(sep) {
+      format!("{}{}", root, joined)
+    } else {
+      format!("{}{}{}", root, sep, joined)
+    };
+    self.0 = Cow::Owned(normalized);
+  }
 
-    if let Some(path) = strip_prefix(&path, "/rustc/") {
-      // TODO: avoid hardcoding this, and instead let users configure
-      // path replacements in DevTools UI.
-      format!("https://raw.githubusercontent.com/rust-lang/rust/{}", path)
+  /// Returns an iterator over the path's components: the root component
+  /// followed by each normal component, splitting on `/` (and additionally on
+  /// `\` for Windows-style paths) and skipping empty and `.` segments.
+  pub fn components(&self) -> Components {
+    let (root_len, sep) = root_len_and_sep(&self.0);
+    Components {
+      root: &self.0[..root_len],
+      rest: &self.0[root_len..],
+      backslash: sep == '\\',
+    }
+  }
+
+  /// Returns the final normal component of the path, or `None` if the path is
+  /// just a root.
+  pub fn file_name(&self) -> Option<&str> {
+    let mut components = self.components();
+    components.next(); // skip the root component
+    components.last()
+  }
+
+  /// Returns the path without its final normal component, or `None` if the
+  /// path is just a root.
+  pub fn parent(&self) -> Option<Path<'static>> {
+    let (root_len, sep) = root_len_and_sep(&self.0);
+    let root = &self.0[..root_len];
+
+    let mut components = self.components();
+    components.next(); // skip the root component
+    let mut normals: Vec<&str> = components.collect();
+    if normals.pop().is_none() {
+      return None;
+    }
+
+    let joined = normals.join(&sep.to_string());
+    let parent = if joined.is_empty() {
+      root.to_string()
+    } else if root.ends_with(sep) {
+      format!("{}{}", root, joined)
+    } else {
+      format!("{}{}{}", root, sep, joined)
+    };
+    Some(Path(Cow::Owned(parent)))
+  }
+
+  pub fn to_uri(&self, mapper: &PathMapper) -> String {
+    let path = de_verbatim(&self.0);
+    let path = path.as_ref();
+
+    if let Some(uri) = mapper.remap(path) {
+      uri
     } else if path.starts_with('/') {
       // Unix-style path
-      format!("file://{}", path)
+      format!("file://{}", encode_uri_path(path))
     } else {
       // Windows-style path
-      format!("file:///{}", path)
+      format!("file:///{}", encode_uri_path(path))
     }
   }
 }
@@ -106,17 +347,32 @@ mod tests {
     assert!(!is_absolute("User\\Someone Special"));
   }
 
+  #[test]
+  pub fn test_is_absolute_verbatim() {
+    assert!(is_absolute("\\\\?\\C:\\foo"));
+    assert!(is_absolute("\\\\?\\UNC\\server\\share\\foo"));
+  }
+
+  #[test]
+  pub fn test_to_uri_verbatim() {
+    let path = Path::new(Cow::from("\\\\?\\C:\\foo"));
+    assert_eq!(path.to_uri(&PathMapper::default()), "file:///C:/foo");
+
+    let path = Path::new(Cow::from("\\\\?\\UNC\\server\\share\\foo"));
+    assert_eq!(path.to_uri(&PathMapper::default()), "file://///server/share/foo");
+  }
+
   #[test]
   pub fn test_path_unix() {
     let mut path = Path::new(Cow::from("/"));
     path.push(Cow::from("etc"));
     path.push(Cow::from("passwd"));
-    assert_eq!(path.to_uri(), "file:///etc/passwd");
+    assert_eq!(path.to_uri(&PathMapper::default()), "file:///etc/passwd");
 
     let mut path = Path::new(Cow::from("/etc"));
     path.push(Cow::from("passwd"));
     path.push(Cow::from("/etc/hosts"));
-    assert_eq!(path.to_uri(), "file:///etc/hosts");
+    assert_eq!(path.to_uri(&PathMapper::default()), "file:///etc/hosts");
   }
 
   #[test]
@@ -124,25 +380,110 @@ mod tests {
     let mut path = Path::new(Cow::from("C:\\"));
     path.push(Cow::from("Windows"));
     path.push(Cow::from("System32"));
-    assert_eq!(path.to_uri(), "file:///C:\\Windows\\System32");
+    assert_eq!(path.to_uri(&PathMapper::default()), "file:///C:/Windows/System32");
 
     let mut path = Path::new(Cow::from("\\\\"));
     path.push(Cow::from("Server"));
     path.push(Cow::from("Share"));
-    assert_eq!(path.to_uri(), "file:///\\\\Server\\Share");
+    assert_eq!(path.to_uri(&PathMapper::default()), "file://///Server/Share");
 
     let mut path = Path::new(Cow::from("a:\\"));
     path.push(Cow::from("Folder"));
     path.push(Cow::from("F:\\Directory\\File.html"));
-    assert_eq!(path.to_uri(), "file:///F:\\Directory\\File.html");
+    assert_eq!(path.to_uri(&PathMapper::default()), "file:///F:/Directory/File.html");
+  }
+
+  #[test]
+  pub fn test_to_uri_percent_encoding() {
+    let path = Path::new(Cow::from("/etc/my file.rs"));
+    assert_eq!(path.to_uri(&PathMapper::default()), "file:///etc/my%20file.rs");
+
+    let path = Path::new(Cow::from("/a/b#c%d.rs"));
+    assert_eq!(path.to_uri(&PathMapper::default()), "file:///a/b%23c%25d.rs");
+
+    // Non-ASCII bytes are percent-encoded one UTF-8 byte at a time.
+    let path = Path::new(Cow::from("/tmp/café.rs"));
+    assert_eq!(path.to_uri(&PathMapper::default()), "file:///tmp/caf%C3%A9.rs");
+
+    let mut path = Path::new(Cow::from("C:\\"));
+    path.push(Cow::from("a b"));
+    path.push(Cow::from("c.rs"));
+    assert_eq!(path.to_uri(&PathMapper::default()), "file:///C:/a%20b/c.rs");
   }
 
   #[test]
   pub fn test_path_rustc() {
     let path = Path::new(Cow::from("/rustc/folder/file.rs"));
     assert_eq!(
-      path.to_uri(),
+      path.to_uri(&PathMapper::default()),
       "https://raw.githubusercontent.com/rust-lang/rust/folder/file.rs"
     );
   }
+
+  #[test]
+  pub fn test_normalize_unix() {
+    let check = |input: &str, expected: &str| {
+      let mut path = Path::new(Cow::from(input));
+      path.normalize();
+      assert_eq!(path.to_uri(&PathMapper::default()), format!("file://{}", expected));
+    };
+    check("/a/b/../c", "/a/c");
+    check("/a/./b", "/a/b");
+    check("/../x", "/x");
+    check("/project/src/../lib/./foo.rs", "/project/lib/foo.rs");
+    check("/a//b///c", "/a/b/c");
+  }
+
+  #[test]
+  pub fn test_normalize_windows() {
+    let mut path = Path::new(Cow::from("C:\\a\\b\\..\\c"));
+    path.normalize();
+    assert_eq!(path.to_uri(&PathMapper::default()), "file:///C:/a/c");
+
+    let mut path = Path::new(Cow::from("\\\\server\\share\\a\\..\\b"));
+    path.normalize();
+    assert_eq!(path.to_uri(&PathMapper::default()), "file://///server/share/b");
+  }
+
+  #[test]
+  pub fn test_components_unix() {
+    let path = Path::new(Cow::from("/tmp/foo/bar.rs"));
+    let components: Vec<&str> = path.components().collect();
+    assert_eq!(components, vec!["/", "tmp", "foo", "bar.rs"]);
+    assert_eq!(path.file_name(), Some("bar.rs"));
+    assert_eq!(
+      path.parent().unwrap().to_uri(&PathMapper::default()),
+      "file:///tmp/foo"
+    );
+  }
+
+  #[test]
+  pub fn test_components_windows() {
+    let path = Path::new(Cow::from("C:\\tmp\\foo\\bar.rs"));
+    let components: Vec<&str> = path.components().collect();
+    assert_eq!(components, vec!["C:\\", "tmp", "foo", "bar.rs"]);
+    assert_eq!(path.file_name(), Some("bar.rs"));
+    assert_eq!(
+      path.parent().unwrap().to_uri(&PathMapper::default()),
+      "file:///C:/tmp/foo"
+    );
+  }
+
+  #[test]
+  pub fn test_components_root_only() {
+    let path = Path::new(Cow::from("/"));
+    assert_eq!(path.file_name(), None);
+    assert!(path.parent().is_none());
+  }
+
+  #[test]
+  pub fn test_path_custom_mapper() {
+    let mut mapper = PathMapper::new();
+    mapper.push("/home/me/project/", "https://myhost/src/");
+    let path = Path::new(Cow::from("/home/me/project/lib.rs"));
+    assert_eq!(path.to_uri(&mapper), "https://myhost/src/lib.rs");
+    // Paths that don't match any rule fall back to `file://`.
+    let other = Path::new(Cow::from("/tmp/other.rs"));
+    assert_eq!(other.to_uri(&mapper), "file:///tmp/other.rs");
+  }
 }